@@ -1,57 +1,374 @@
+extern crate cc;
+extern crate pkg_config;
+
 use std::env;
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
-/// The command to build lua, with switches for different OSes.
-fn build_lua_native(dir: &Path) -> io::Result<()> {
-    let platform = if cfg!(target_os = "windows") {
-        "mingw"
-    } else if cfg!(target_os = "macos") {
-        "macosx"
-    } else if cfg!(target_os = "linux") {
-        "linux"
-    } else if cfg!(target_os = "freebsd") {
-        "freebsd"
-    } else if cfg!(target_os = "dragonfly") {
-        "bsd"
-    } else {
-        panic!("Unsupported target OS")
-    };
+/// A system Lua install located by `find_system_lua`.
+///
+/// Carries everything needed to link against it, but doesn't emit any
+/// `cargo:rustc-link-*` metadata itself -- the caller must run
+/// `check_lua_version` against `include_dir` first, so we never link a
+/// library whose headers turn out to be the wrong version.
+struct SystemLua {
+    include_dir: PathBuf,
+    link_paths: Vec<PathBuf>,
+    libs: Vec<String>,
+}
 
-    if cfg!(any(target_os = "linux", target_os = "freebsd", target_os = "bsd")) {
-        run_command(&["make", platform, "MYCFLAGS=-fPIC"], Some(dir))
-    } else {
-        run_command(&["make", platform], Some(dir))
+/// Looks for an already-installed Lua of the given version to link against instead of
+/// building our own copy from source.
+///
+/// `LUA_INC`/`LUA_LIB`/`LUA_LIB_NAME` are honored first so users can point
+/// the build at an arbitrary Lua install; otherwise we probe for it with
+/// `pkg-config`.
+fn find_system_lua(version: &LuaVersion) -> Option<SystemLua> {
+    println!("cargo:rerun-if-env-changed=LUA_INC");
+    println!("cargo:rerun-if-env-changed=LUA_LIB");
+    println!("cargo:rerun-if-env-changed=LUA_LIB_NAME");
+
+    if let Ok(lib_dir) = env::var("LUA_LIB") {
+        // Both variables must be given together; without LUA_INC we can't
+        // validate the version or compile the glue code against it, and
+        // silently falling through to also building a vendored/static Lua
+        // would double-link against whatever LUA_LIB pointed at.
+        let lua_inc = match env::var("LUA_INC") {
+            Ok(lua_inc) => lua_inc,
+            Err(_) => panic!("LUA_LIB is set but LUA_INC is not; both must be provided together"),
+        };
+
+        let lib_name = env::var("LUA_LIB_NAME").unwrap_or(format!("lua{}", &version.version[..3]));
+
+        return Some(SystemLua {
+            include_dir: PathBuf::from(lua_inc),
+            link_paths: vec![PathBuf::from(lib_dir)],
+            libs: vec![lib_name],
+        });
+    }
+
+    // Constrain the probe to the crate's expected minor version: pkg-config
+    // only understands a lower bound, so a 5.4+ install would otherwise be
+    // accepted here and only rejected later by `check_lua_version`. We
+    // disable pkg-config's automatic cargo metadata entirely -- even the
+    // `.pc` version matching the short version isn't proof enough that
+    // `lua.h`'s `LUA_VERSION_NUM` agrees, so the caller emits link metadata
+    // itself only after `check_lua_version` has passed.
+    let short_version = &version.version[..3];
+    for name in &[format!("lua{}", short_version), format!("lua-{}", short_version), "lua".to_string()] {
+        if let Ok(lib) = pkg_config::Config::new().atleast_version(version.version).cargo_metadata(false).probe(name) {
+            if !lib.version.starts_with(short_version) {
+                continue;
+            }
+
+            let include_dir = match lib.include_paths.into_iter().next() {
+                Some(include_dir) => include_dir,
+                None => continue,
+            };
+
+            return Some(SystemLua {
+                include_dir: include_dir,
+                link_paths: lib.link_paths,
+                libs: lib.libs,
+            });
+        }
     }
+
+    None
 }
 
-fn build_lua_target(dir: &Path) -> io::Result<()> {
-    let cc = env::var("CC").unwrap_or("gcc".to_string());
+/// Describes one of the Lua versions this crate knows how to build, as
+/// selected by the mutually-exclusive `lua51`/`lua52`/`lua53` cargo
+/// features.
+struct LuaVersion {
+    /// The full release version, e.g. "5.3.0".
+    version: &'static str,
+    /// The value of `LUA_VERSION_NUM` that release defines.
+    version_num: i32,
+}
 
-    let target = if let Some(target) = env::var("TARGET").ok().and_then(|var| var.split('-').nth(2).map(|s| s.to_string())) {
-        target
-    } else {
-        panic!("Unknown target OS")
-    };
+impl LuaVersion {
+    /// Determines the Lua version to build from the enabled cargo feature,
+    /// panicking unless exactly one version feature is enabled.
+    fn current() -> LuaVersion {
+        let enabled = cfg!(feature = "lua51") as u8 + cfg!(feature = "lua52") as u8 + cfg!(feature = "lua53") as u8;
+        if enabled != 1 {
+            panic!("exactly one of the `lua51`, `lua52`, or `lua53` features must be enabled, but {} were", enabled);
+        }
 
-    let platform = match &target as &str {
-        "windows" => { "mingw" }
-        "darwin" => { "macosx" }
-        "linux" => { "linux" }
-        "freebsd" => { "freebsd" }
-        "dragonfly" => { "bsd" }
-        _ =>  {
-            panic!("Unsupported target OS")
+        if cfg!(feature = "lua51") {
+            LuaVersion { version: "5.1.5", version_num: 501 }
+        } else if cfg!(feature = "lua52") {
+            LuaVersion { version: "5.2.4", version_num: 502 }
+        } else {
+            LuaVersion { version: "5.3.0", version_num: 503 }
         }
-    };
+    }
+
+    /// The name of the directory the release tarball extracts into, e.g.
+    /// "lua-5.3.0".
+    fn dir_name(&self) -> String {
+        format!("lua-{}", self.version)
+    }
+
+    /// The URL of the release tarball on lua.org.
+    fn url(&self) -> String {
+        format!("http://www.lua.org/ftp/lua-{}.tar.gz", self.version)
+    }
+}
+
+/// Reads `LUA_VERSION_NUM` out of `lua.h` in the given include directory.
+///
+/// This is how we sanity-check a system or user-provided Lua before linking
+/// against it, since the crate's generated bindings are only correct for one
+/// ABI at a time.
+fn lua_version_num(include_dir: &Path) -> io::Result<i32> {
+    let header = try!(fs::File::open(include_dir.join("lua.h")));
+    let reader = io::BufReader::new(header);
+
+    for line in io::BufRead::lines(reader) {
+        let line = try!(line);
+        let mut tokens = line.split_whitespace();
+        if tokens.next() == Some("#define") && tokens.next() == Some("LUA_VERSION_NUM") {
+            if let Some(num) = tokens.next() {
+                if let Ok(num) = num.parse() {
+                    return Ok(num);
+                }
+            }
+        }
+    }
+
+    Err(io::Error::new(io::ErrorKind::NotFound, format!(
+        "could not find LUA_VERSION_NUM in {}", include_dir.join("lua.h").display())))
+}
+
+/// Checks that the Lua found at `include_dir` matches `expected`'s
+/// `LUA_VERSION_NUM`, panicking with a clear message if not.
+fn check_lua_version(include_dir: &Path, expected: &LuaVersion) -> io::Result<()> {
+    let found = try!(lua_version_num(include_dir));
+
+    if found != expected.version_num {
+        panic!("found Lua with LUA_VERSION_NUM {} at {}, but the enabled cargo feature expects {}",
+            found, include_dir.display(), expected.version_num);
+    }
+
+    println!("cargo:rustc-cfg=lua_version_num=\"{}\"", found);
 
-    if platform == "linux" || platform == "freebsd" || platform == "bsd" {
-        run_command(&["make", platform, "MYCFLAGS=-fPIC"], Some(dir))
+    Ok(())
+}
+
+/// Compiles the C sources of an extracted Lua release tree into a static
+/// `liblua.a` using the `cc` crate, writing the archive to `out_dir` and
+/// targeting `target`/`host` (empty strings fall back to `cc`'s own
+/// environment-based defaults).
+///
+/// `cc` already knows the right compiler, sysroot, and flags for the
+/// active `TARGET` (including `cl.exe` on `*-msvc`, where there is no
+/// `make`), so this replaces shelling out to Lua's own Makefile. We skip
+/// `lua.c` and `luac.c`, which are the standalone interpreter/compiler
+/// `main()`s and not part of the library.
+fn compile_lua(lua_dir: &Path, out_dir: &Path, target: &str, host: &str) -> io::Result<()> {
+    let src_dir = lua_dir.join("src");
+    let mut build = cc::Build::new();
+    build.include(&src_dir);
+    build.out_dir(out_dir);
+    if !target.is_empty() {
+        build.target(target);
+    }
+    if !host.is_empty() {
+        build.host(host);
+    }
+
+    let platform = resolve_target_platform();
+    for define in platform.lua_use_defines {
+        build.define(*define, None);
+    }
+    if let Some(flag) = platform.pic_flag {
+        build.flag(flag);
+    }
+
+    for entry in try!(fs::read_dir(&src_dir)) {
+        let path = try!(entry).path();
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_string();
+        let is_main = name == "lua.c" || name == "luac.c";
+
+        if path.extension().map_or(false, |ext| ext == "c") && !is_main {
+            build.file(path);
+        }
+    }
+
+    build.compile("lua");
+
+    Ok(())
+}
+
+/// A builder for compiling a vendored copy of Lua.
+///
+/// This exposes the handful of inputs a build script would otherwise read
+/// straight from the environment (`OUT_DIR`, `TARGET`, `HOST`) as plain
+/// fields that can be set explicitly. The `LUA_USE_*` defines and the PIC
+/// flag are still chosen from the `CARGO_CFG_TARGET_*` environment
+/// variables, not from `target`, and building panics when those are unset.
+pub struct Build {
+    out_dir: PathBuf,
+    target: String,
+    host: String,
+}
+
+/// The result of a successful `Build::build`: where the compiled Lua's
+/// headers and static library ended up.
+pub struct Artifacts {
+    include_dir: PathBuf,
+    lib_dir: PathBuf,
+}
+
+impl Build {
+    pub fn new() -> Build {
+        Build {
+            out_dir: env::var("OUT_DIR").map(PathBuf::from).unwrap_or_default(),
+            target: env::var("TARGET").unwrap_or_default(),
+            host: env::var("HOST").unwrap_or_default(),
+        }
+    }
+
+    pub fn out_dir<P: AsRef<Path>>(&mut self, out_dir: P) -> &mut Build {
+        self.out_dir = out_dir.as_ref().to_path_buf();
+        self
+    }
+
+    pub fn target(&mut self, target: &str) -> &mut Build {
+        self.target = target.to_string();
+        self
+    }
+
+    pub fn host(&mut self, host: &str) -> &mut Build {
+        self.host = host.to_string();
+        self
+    }
+
+    /// Compiles the vendored sources for `version` from `src_dir` (an
+    /// extracted or in-crate Lua release tree), honoring `self.out_dir`/
+    /// `self.target`/`self.host`, and validates the result actually is
+    /// `version`.
+    pub fn build(&self, version: &LuaVersion, src_dir: &Path) -> io::Result<Artifacts> {
+        try!(compile_lua(src_dir, &self.out_dir, &self.target, &self.host));
+
+        let include_dir = src_dir.join("src");
+        try!(check_lua_version(&include_dir, version));
+
+        Ok(Artifacts {
+            include_dir: include_dir,
+            lib_dir: self.out_dir.clone(),
+        })
+    }
+}
+
+impl Artifacts {
+    pub fn include_dir(&self) -> &Path {
+        &self.include_dir
+    }
+
+    pub fn lib_dir(&self) -> &Path {
+        &self.lib_dir
+    }
+}
+
+/// The LuaJIT release this crate builds when the `luajit` feature is
+/// enabled.
+const LUAJIT_VERSION: &'static str = "2.1.0-beta3";
+
+fn luajit_dir_name() -> String {
+    format!("LuaJIT-{}", LUAJIT_VERSION)
+}
+
+fn luajit_url() -> String {
+    format!("http://luajit.org/download/{}.tar.gz", luajit_dir_name())
+}
+
+/// The platform identifiers a Lua/LuaJIT build needs, resolved from the
+/// `CARGO_CFG_TARGET_*` variables Cargo sets for the real compilation
+/// target rather than by slicing up the `TARGET` triple by hand. The old
+/// approach (`TARGET.split('-').nth(2)`) silently misidentified triples
+/// such as `x86_64-pc-windows-msvc` and panicked on anything it didn't
+/// expect; reading `CARGO_CFG_TARGET_OS`/`_ARCH`/`_ENV` Cargo already
+/// computed is both more correct and gives a clear error for genuinely
+/// unsupported platforms/architectures. Both PUC Lua (`compile_lua`'s
+/// `LUA_USE_*` defines and `-fPIC`) and LuaJIT (`build_luajit`'s
+/// `TARGET_SYS`) resolve their platform through this one function.
+struct TargetPlatform {
+    /// The `TARGET_SYS` value LuaJIT's `Makefile` expects.
+    luajit_target_sys: &'static str,
+    /// The `LUA_USE_*` preprocessor defines PUC Lua expects on this
+    /// platform.
+    lua_use_defines: &'static [&'static str],
+    /// The compiler flag needed to produce position-independent code, if
+    /// any (MSVC and the GNU toolchain on Windows don't use one).
+    pic_flag: Option<&'static str>,
+}
+
+/// The `CARGO_CFG_TARGET_ARCH` values this crate's C sources are known to
+/// compile cleanly on.
+const SUPPORTED_ARCHES: &'static [&'static str] =
+    &["x86", "x86_64", "arm", "aarch64", "mips", "mips64", "powerpc", "powerpc64"];
+
+fn resolve_target_platform() -> TargetPlatform {
+    let os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    let arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+    let env_ = env::var("CARGO_CFG_TARGET_ENV").unwrap_or_default();
+
+    if !SUPPORTED_ARCHES.contains(&arch.as_str()) {
+        panic!("unsupported target architecture for rust-lua53 (CARGO_CFG_TARGET_ARCH={:?}); \
+            supported architectures are {:?}", arch, SUPPORTED_ARCHES);
+    }
+
+    let (luajit_target_sys, lua_use_defines, pic_flag): (&'static str, &'static [&'static str], Option<&'static str>) =
+        match (os.as_str(), env_.as_str()) {
+            ("linux", _) => ("Linux", &["LUA_USE_LINUX"], Some("-fPIC")),
+            ("macos", _) => ("Darwin", &["LUA_USE_MACOSX"], Some("-fPIC")),
+            ("windows", "gnu") => ("Windows", &["LUA_USE_WINDOWS"], None),
+            ("windows", "msvc") => ("Windows", &["LUA_USE_WINDOWS"], None),
+            ("freebsd", _) => ("BSD", &["LUA_USE_POSIX", "LUA_USE_DLOPEN"], Some("-fPIC")),
+            ("dragonfly", _) => ("BSD", &["LUA_USE_POSIX", "LUA_USE_DLOPEN"], Some("-fPIC")),
+            _ => panic!("unsupported target for rust-lua53 (CARGO_CFG_TARGET_OS={:?}, CARGO_CFG_TARGET_ENV={:?}); \
+                supported platforms are linux, macos, windows-gnu, windows-msvc, freebsd, and dragonfly", os, env_),
+        };
+
+    TargetPlatform {
+        luajit_target_sys: luajit_target_sys,
+        lua_use_defines: lua_use_defines,
+        pic_flag: pic_flag,
+    }
+}
+
+/// Builds LuaJIT in `dir` (the extracted release tree) for the active
+/// `TARGET`.
+///
+/// Unlike PUC Lua, LuaJIT is built from the top-level `Makefile` rather
+/// than `src/Makefile`, and cross-compiling means passing `HOST_CC`,
+/// `CROSS`, and `TARGET_SYS` instead of selecting one of a handful of
+/// named platforms.
+fn build_luajit(dir: &Path) -> io::Result<()> {
+    let platform = resolve_target_platform();
+    let make = if cfg!(any(target_os = "freebsd", target_os = "dragonfly")) {
+        "gmake"
     } else {
-        run_command(&["make", platform, &format!("CC={}", &cc)], Some(dir))
+        "make"
+    };
+
+    let host_cc = env::var("HOST_CC").or_else(|_| env::var("CC")).unwrap_or("gcc".to_string());
+    let mut args = vec![make.to_string(), "BUILDMODE=static".to_string(), format!("HOST_CC={}", host_cc)];
+
+    let target = env::var("TARGET").unwrap_or_default();
+    let host = env::var("HOST").unwrap_or_default();
+    if !target.is_empty() && target != host {
+        args.push(format!("CROSS={}-", target));
+        args.push(format!("TARGET_SYS={}", platform.luajit_target_sys));
     }
+
+    let arg_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    run_command(&arg_refs, Some(dir))
 }
 
 /// The command to fetch a URL (e.g. with wget) specialized for different
@@ -88,49 +405,138 @@ fn run_command(all_args: &[&str], cwd: Option<&Path>) -> io::Result<()> {
     Ok(())
 }
 
+/// Downloads, builds, and links against LuaJIT, returning its include
+/// directory for the glue compilation step.
+fn prebuild_luajit(out_dir: &str, build_dir: &Path) -> io::Result<PathBuf> {
+    let luajit_dir = PathBuf::from(out_dir).join(luajit_dir_name());
+
+    if !fs::metadata(luajit_dir.join("src").join("libluajit.a")).is_ok() {
+        try!(fs::create_dir_all(build_dir));
+
+        let tarball = build_dir.join(format!("{}.tar.gz", luajit_dir_name()));
+        if !fs::metadata(&tarball).is_ok() {
+            try!(fetch_in_dir(&luajit_url(), Some(build_dir)));
+            try!(run_command(&["tar", "xzf", &format!("{}.tar.gz", luajit_dir_name())], Some(build_dir)));
+        }
+
+        try!(build_luajit(luajit_dir.as_path()));
+    }
+
+    println!("cargo:rustc-link-lib=static=luajit");
+    println!("cargo:rustc-link-search=native={}", luajit_dir.join("src").display());
+
+    Ok(luajit_dir.join("src"))
+}
+
+/// Downloads (or locates) and builds PUC Lua for the version selected by
+/// the `lua51`/`lua52`/`lua53` features, returning its include directory
+/// for the glue compilation step.
+fn prebuild_lua(out_dir: &str, build_dir: &Path) -> io::Result<PathBuf> {
+    let version = LuaVersion::current();
+    let lua_dir = PathBuf::from(out_dir).join(version.dir_name());
+
+    // If the `vendored` feature is off, prefer a Lua that's already on the
+    // system over downloading and building our own.
+    let system_lua = if cfg!(feature = "vendored") {
+        None
+    } else {
+        find_system_lua(&version)
+    };
+
+    if let Some(system_lua) = system_lua {
+        try!(check_lua_version(&system_lua.include_dir, &version));
+
+        for path in &system_lua.link_paths {
+            println!("cargo:rustc-link-search=native={}", path.display());
+        }
+        for lib_name in &system_lua.libs {
+            println!("cargo:rustc-link-lib=dylib={}", lib_name);
+        }
+
+        return Ok(system_lua.include_dir);
+    }
+
+    // Prefer the Lua sources vendored in-crate under `lua/`, which keeps a
+    // normal build hermetic and working offline. Downloading a release
+    // tarball over the network is only ever an opt-in fallback, enabled
+    // with the `download` feature, for the case where a version hasn't
+    // been vendored and no system Lua was found.
+    let vendor_dir = PathBuf::from("lua").join(version.dir_name());
+
+    let src_dir = if fs::metadata(&vendor_dir).is_ok() {
+        vendor_dir
+    } else if cfg!(feature = "download") {
+        if !fs::metadata(lua_dir.join("src")).is_ok() {
+            try!(fs::create_dir_all(build_dir));
+
+            let tarball = build_dir.join(format!("{}.tar.gz", version.dir_name()));
+            if !fs::metadata(&tarball).is_ok() {
+                try!(fetch_in_dir(&version.url(), Some(build_dir)));
+                try!(run_command(&["tar", "xzf", &format!("{}.tar.gz", version.dir_name())], Some(build_dir)));
+            }
+        }
+
+        lua_dir
+    } else {
+        panic!("no vendored Lua sources found at {} and the `download` feature (which would fetch \
+            them over the network) is disabled; either vendor the sources or enable `download`",
+            vendor_dir.display());
+    };
+
+    let artifacts = try!(Build::new().out_dir(build_dir).build(&version, &src_dir));
+    Ok(artifacts.include_dir().to_path_buf())
+}
+
+/// Compiles `src/glue/glue.c` against the headers in `include_dir` and runs
+/// it to generate `glue.rs`, which the crate includes to get at constants
+/// that aren't otherwise exposed to Rust.
+///
+/// Reuses a `cc::Build` (rather than invoking `gcc` directly) so this
+/// works with whatever compiler `cc` picked, including `cl.exe` when
+/// building with MSVC. Glue is a code generator we run ourselves during
+/// the build, not part of the crate, so it must always be compiled for
+/// and run on the host -- `cc::Build` defaults to `TARGET`, which would
+/// produce a binary we can't execute when cross-compiling.
+fn build_glue(out_dir: &str, include_dir: &Path) -> io::Result<()> {
+    let glue_rs = format!("{}/glue.rs", out_dir);
+    if fs::metadata(&glue_rs).is_ok() {
+        return Ok(());
+    }
+
+    let host = env::var("HOST").unwrap_or_default();
+    let glue_exe = PathBuf::from(out_dir).join(format!("glue{}", env::consts::EXE_SUFFIX));
+    let compiler = cc::Build::new().include(include_dir).target(&host).host(&host).get_compiler();
+
+    let mut command = compiler.to_command();
+    command.arg("src/glue/glue.c");
+    if compiler.is_like_msvc() {
+        command.arg(format!("/Fe{}", glue_exe.display()));
+    } else {
+        command.arg("-o").arg(&glue_exe);
+    }
+
+    let status = try!(command.status());
+    if !status.success() {
+        return Err(io::Error::new(io::ErrorKind::Other, "failed to compile src/glue/glue.c"));
+    }
+
+    run_command(&[glue_exe.to_str().unwrap(), &glue_rs], None)
+}
+
 /// If a static Lua is not yet available from a prior run of this script, this
 /// will download Lua and build it. The cargo configuration text to link
 /// statically against lua.a is then printed to stdout.
 fn prebuild() -> io::Result<()> {
     let out_dir = env::var("OUT_DIR").unwrap();
     let build_dir = PathBuf::from(&out_dir);
-    let lua_dir = PathBuf::from(&format!("{}/lua-5.3.0", &out_dir));
 
-    // Ensure the presence of liblua.a
-    if !fs::metadata(&format!("{}/lua-5.3.0/src/liblua.a", out_dir)).is_ok() {
-        try!(fs::create_dir_all(build_dir.as_path()));
+    let include_dir = if cfg!(feature = "luajit") {
+        try!(prebuild_luajit(&out_dir, build_dir.as_path()))
+    } else {
+        try!(prebuild_lua(&out_dir, build_dir.as_path()))
+    };
 
-        // Download lua if it hasn't been already
-        if !fs::metadata(&format!("{}/lua-5.3.0.tar.gz", &out_dir)).is_ok() {
-            println!("{:?}", out_dir);
-            try!(fetch_in_dir("http://www.lua.org/ftp/lua-5.3.0.tar.gz", Some(build_dir.as_path())));
-            try!(run_command(&["tar", "xzf", "lua-5.3.0.tar.gz"], Some(build_dir.as_path())));
-        }
-        // Compile lua
-        try!(run_command(&["make", "clean"], Some(lua_dir.as_path())));
-        try!(build_lua_native(lua_dir.as_path()));
-    }
-
-    // Ensure the presence of glue.rs
-    if !fs::metadata(&format!("{}/glue.rs", out_dir)).is_ok() {
-        // Compile glue.c
-        let glue = format!("{}/glue", out_dir);
-        try!(run_command(&["gcc",
-                         "-I", &format!("{}/lua-5.3.0/src", &out_dir),
-                         "src/glue/glue.c",
-                         "-o", &glue], None));
-        // Run glue to generate glue.rs
-        try!(run_command(&[&glue, &format!("{}/glue.rs", out_dir)], None));
-    }
-
-    // Build lua for the specified target
-    try!(run_command(&["make", "clean"], Some(lua_dir.as_path())));
-    // Compile lua
-    try!(build_lua_target(lua_dir.as_path()));
-
-    // Output build information
-    println!("cargo:rustc-link-lib=static=lua");
-    println!("cargo:rustc-link-search=native={}/lua-5.3.0/src", &out_dir);
+    try!(build_glue(&out_dir, &include_dir));
 
     Ok(())
 }